@@ -13,7 +13,54 @@ struct SequenceSet {
 }
 struct SeqSpan {
     start: u64,
-    count: u16,
+    count: u64,
+}
+
+impl SequenceSet {
+    fn new() -> SequenceSet {
+        SequenceSet { spans: Vec::new() }
+    }
+
+    /// Insert the half-open run `[start, start + count)`, coalescing it with any adjacent or
+    /// overlapping spans already present so storage stays O(number of discontiguous runs) however
+    /// long the stream runs.
+    fn insert(&mut self, start: u64, count: u64) {
+        if count == 0 {
+            return;
+        }
+        let mut lo = start;
+        let mut hi = start + count; // exclusive
+        let mut result = Vec::with_capacity(self.spans.len() + 1);
+        let mut placed = false;
+        for span in self.spans.drain(..) {
+            let span_lo = span.start;
+            let span_hi = span.start + span.count;
+            if span_hi < lo {
+                // entirely before the run being inserted
+                result.push(span);
+            } else if span_lo > hi {
+                // entirely after; emit the merged run first to keep spans ordered
+                if !placed {
+                    result.push(SeqSpan { start: lo, count: hi - lo });
+                    placed = true;
+                }
+                result.push(span);
+            } else {
+                // adjacent or overlapping: absorb it
+                lo = lo.min(span_lo);
+                hi = hi.max(span_hi);
+            }
+        }
+        if !placed {
+            result.push(SeqSpan { start: lo, count: hi - lo });
+        }
+        self.spans = result;
+    }
+
+    /// Test whether `value` has previously been inserted.
+    fn contains(&self, value: u64) -> bool {
+        self.spans.iter().any(|s| value >= s.start && value < s.start + s.count)
+    }
 }
 
 struct PlaylistInfo {
@@ -21,6 +68,34 @@ struct PlaylistInfo {
     href: HttpRef,
 }
 
+/// The operating mode of a media playlist, derived from `EXT-X-PLAYLIST-TYPE`.  Each mode carries
+/// its own mutation invariant: a `Live` window slides, an `Event` only ever appends, and a `Vod`
+/// is fixed.  The one legal lifecycle transition is `Event` completing into `Vod` once
+/// `EXT-X-ENDLIST` is published.
+#[derive(Copy, Clone, PartialEq, Eq)]
+enum PlaylistMode {
+    Live,
+    Event,
+    Vod,
+}
+
+impl PlaylistMode {
+    fn of(playlist: &hls_m3u8::parser::MyMediaPlaylist) -> PlaylistMode {
+        match playlist.playlist_type {
+            Some(hls_m3u8::types::PlaylistType::Event) => PlaylistMode::Event,
+            Some(hls_m3u8::types::PlaylistType::Vod) => PlaylistMode::Vod,
+            None => PlaylistMode::Live,
+        }
+    }
+}
+
+/// Whether a mid-stream `playlist_type` change from `from` to `to` is legitimate.  The sole legal
+/// lifecycle transition is an `EVENT` completing into a (pseudo-)`VOD`, and only once
+/// `EXT-X-ENDLIST` has been published; every other change is a fault.
+fn legal_mode_transition(from: PlaylistMode, to: PlaylistMode, has_end_list: bool) -> bool {
+    from == PlaylistMode::Event && to == PlaylistMode::Vod && has_end_list
+}
+
 enum LastError {
     None,
     HttpError(u16),
@@ -37,6 +112,19 @@ pub struct MediaPlaylistCheck<L: EventSink<Extra = HlsEvent>, M: Metric> {
     ended: bool,
     msn_regression: M,
     last_error: LastError,
+    last_discontinuity_sequence: Option<u64>,
+    // the encryption method(s) and KEYFORMAT/KEYFORMATVERSIONS pair(s) carried by the previous
+    // segment's full set of EXT-X-KEY tags, kept sorted so multi-DRM segments compare as a set
+    last_encryption_methods: Option<Vec<String>>,
+    last_key_formats: Option<Vec<(Option<String>, Option<String>)>>,
+    // implicit-IV derivation is spec-compliant and ubiquitous, so the missing-IV warning is
+    // raised at most once for the lifetime of the stream rather than on every segment
+    warned_missing_iv: bool,
+    observed_msns: SequenceSet,
+    // the URI each observed MSN was first served with, so a reappearing number is only flagged
+    // when it comes back pointing at different media
+    observed_uris: std::collections::HashMap<u64, String>,
+    msn_live_edge: Option<u64>,
 }
 
 fn delta(before: &PlaylistInfo, after: &PlaylistInfo) -> Delta {
@@ -64,7 +152,45 @@ impl<L: EventSink<Extra = HlsEvent>, M: Metric> MediaPlaylistCheck<L, M> {
             ended: false,
             msn_regression,
             last_error: LastError::None,
+            last_discontinuity_sequence: None,
+            last_encryption_methods: None,
+            last_key_formats: None,
+            warned_missing_iv: false,
+            observed_msns: SequenceSet::new(),
+            observed_uris: std::collections::HashMap::new(),
+            msn_live_edge: None,
+        }
+    }
+
+    /// Produce the current [`RenditionSyncState`] for feeding into a [`RenditionCoordinator`], or
+    /// `None` if no playlist has been observed yet.  Per-segment PDTs are resolved with the same
+    /// anchor-and-accumulate rule as the drift check.
+    pub fn sync_state(&self) -> Option<RenditionSyncState> {
+        let info = self.last_playlist.as_ref()?;
+        let playlist = &info.playlist;
+        let mut first = None;
+        let mut last = None;
+        let mut boundaries = Vec::new();
+        let mut pdt = PdtTracker::new();
+        for seg in playlist.segments() {
+            let (derived, _) = pdt.advance(seg.has_discontinuity(), seg.program_date_time(), seg.duration().duration());
+            if let Some(seg_pdt) = derived {
+                if first.is_none() {
+                    first = Some(seg_pdt);
+                }
+                last = Some(seg_pdt);
+                if seg.has_discontinuity() {
+                    boundaries.push(seg_pdt);
+                }
+            }
         }
+        Some(RenditionSyncState {
+            href: info.href.clone(),
+            first_program_date_time: first,
+            last_program_date_time: last,
+            discontinuity_boundaries: boundaries,
+            target_duration: playlist.target_duration,
+        })
     }
 
     pub fn not_modified(&mut self) {
@@ -104,6 +230,9 @@ impl<L: EventSink<Extra = HlsEvent>, M: Metric> MediaPlaylistCheck<L, M> {
 
     pub fn next_playlist(&mut self, href: HttpRef, playlist: hls_m3u8::parser::MyMediaPlaylist, total_time: time::Duration) {
         self.last_error = LastError::None;
+        // remember where the previous refresh ended so we only scan newly appended segments below
+        let prev_last_msn = self.last_playlist.as_ref()
+            .and_then(|p| p.playlist.last_segment().map(|s| s.number()));
         let playlist_info = PlaylistInfo {
             href: href.clone(),
             playlist,
@@ -113,10 +242,15 @@ impl<L: EventSink<Extra = HlsEvent>, M: Metric> MediaPlaylistCheck<L, M> {
             self.check_update(&last_playlist, &playlist_info);
         } else {
             self.check_initial_configuration(&playlist_info);
+            self.last_discontinuity_sequence = Some(playlist_info.playlist.discontinuity_sequence);
+            self.record_observed_msns(&playlist_info, playlist_info.playlist.segments());
             self.timeline.append_new_segments(playlist_info.playlist.segments());
             // being the first copy of the playlist we've seen, it can't be stale,
             self.last_fresh_playlist_req = Some(playlist_info.href.clone());
         }
+        self.check_segment_target_duration(&playlist_info, prev_last_msn);
+        self.check_encryption(&playlist_info, prev_last_msn);
+        self.check_program_date_time_drift(&playlist_info, prev_last_msn);
         self.check_headers(&playlist_info);
         // TODO: consider tuning the alert-level down.
         if total_time >= playlist_info.playlist.target_duration {
@@ -189,27 +323,35 @@ impl<L: EventSink<Extra = HlsEvent>, M: Metric> MediaPlaylistCheck<L, M> {
                     .map(ToOwned::to_owned),
             })
         }
-        // TODO: this is not legitimate mid playback, however it's also not a problem we're seeing
-        //       on normal usage, and is also guaranteed to happen at the env of every event when
-        //       switching to 'pseudo-vod' mode.  Therefore suppressing for now to avoid false
-        //       negative alerts,
-        //if last.playlist.playlist_type != this.playlist.playlist_type {
-        //    self.log.error(HlsEvent::PlaylistTypeChanged {
-        //        last_type: last.playlist.playlist_type,
-        //        this_type: this.playlist.playlist_type,
-        //    })
-        //}
+        let last_mode = PlaylistMode::of(&last.playlist);
+        let this_mode = PlaylistMode::of(&this.playlist);
+        if last_mode != this_mode {
+            // The only legitimate change mid-stream is an EVENT completing into a (pseudo-)VOD
+            // once EXT-X-ENDLIST appears.  Everything else is an alert.
+            if !legal_mode_transition(last_mode, this_mode, this.playlist.has_end_list) {
+                self.log.error(HlsEvent::PlaylistTypeChanged {
+                    last_type: last.playlist.playlist_type,
+                    this_type: this.playlist.playlist_type,
+                })
+            }
+        }
     }
 
     fn check_update(&mut self, last: &PlaylistInfo, this: &PlaylistInfo) {
-        // TODO: assert that the EXT-X-PROGRAM-DATE-TIME values continue to match up with the segments as items are removed from the top of the playlist etc
-
         // TODO: handle playlists that are empty, without panicking
 
         // Once the stream ends, it doesn't make sense for it to start again
         if last.playlist.has_end_list && !this.playlist.has_end_list {
             self.log.warning(HlsEvent::EndListTagRemoved)
         }
+        // A VOD playlist is fixed for the stream lifetime: it must carry EXT-X-ENDLIST and its
+        // segment list must never change.  (Head removal is caught as an error below; here we
+        // enforce the completeness requirement.)
+        if PlaylistMode::of(&this.playlist) == PlaylistMode::Vod && !this.playlist.has_end_list {
+            self.log.error(HlsEvent::VodMissingEndList {
+                delta: delta(last, this),
+            })
+        }
         // if the MSN changes, it should only ever increase
         if last.playlist.media_sequence > this.playlist.media_sequence {
             let regression = last.playlist.media_sequence - this.playlist.media_sequence;
@@ -221,20 +363,71 @@ impl<L: EventSink<Extra = HlsEvent>, M: Metric> MediaPlaylistCheck<L, M> {
             })
         } else {
             self.msn_regression.put(0);
+            // Head removal on a sliding window advances the media sequence while the newest
+            // segment keeps growing, so it never reaches the tail-regression branch below.  For
+            // EVENT (and the fixed VOD) playlists the head must never move — flag it here.
+            if this.playlist.media_sequence > last.playlist.media_sequence {
+                if let PlaylistMode::Event | PlaylistMode::Vod = PlaylistMode::of(&this.playlist) {
+                    self.log.error(HlsEvent::EventPlaylistSegmentsRemoved {
+                        delta: delta(last, this),
+                        last_msn: last.playlist.media_sequence,
+                        this_msn: this.playlist.media_sequence,
+                        removed_count: this.playlist.media_sequence - last.playlist.media_sequence,
+                    });
+                }
+            }
+            // A VOD is fixed for the stream's lifetime, so appending to its segment list is just
+            // as illegal as removing from the head (handled above); flag any growth of the tail.
+            if PlaylistMode::of(&this.playlist) == PlaylistMode::Vod {
+                if let (Some(last_end), Some(this_end)) = (
+                    last.playlist.last_segment().map(|s| s.number()),
+                    this.playlist.last_segment().map(|s| s.number()),
+                ) {
+                    if this_end > last_end {
+                        self.log.error(HlsEvent::VodPlaylistMutated {
+                            delta: delta(last, this),
+                            last_msn: last_end,
+                            this_msn: this_end,
+                        });
+                    }
+                }
+            }
+            // count the EXT-X-DISCONTINUITY tags on segments that have rolled off the head of the
+            // window since the last refresh; the discontinuity sequence must advance by exactly
+            // that many
+            let dropped_discontinuities = last.playlist.segments()
+                .take_while(|s| s.number() < this.playlist.media_sequence)
+                .filter(|s| s.has_discontinuity())
+                .count();
+            self.check_discontinuity_sequence(last, this, dropped_discontinuities);
             if last.playlist.last_segment().unwrap().number() > this.playlist.last_segment().unwrap().number() {
                 let removed_count = last.playlist.last_segment().unwrap().number() - this.playlist.last_segment().unwrap().number();
-                let event = HlsEvent::LiveSegmentsRemoved {
-                    delta: delta(&last, &this),
-                    last_msn: last.playlist.last_segment().unwrap().number(),
-                    this_msn: this.playlist.last_segment().unwrap().number(),
-                    removed_count
-                };
-                if removed_count > 1 {
-                    self.log.error(event);
-                } else {
-                    self.log.warning(event);
+                // For EVENT (and the fixed VOD) playlists, segments must never be removed, so
+                // segments vanishing from the tail are a hard error rather than the live-window
+                // warning.
+                match PlaylistMode::of(&this.playlist) {
+                    PlaylistMode::Event | PlaylistMode::Vod => {
+                        self.log.error(HlsEvent::EventPlaylistSegmentsRemoved {
+                            delta: delta(&last, &this),
+                            last_msn: last.playlist.last_segment().unwrap().number(),
+                            this_msn: this.playlist.last_segment().unwrap().number(),
+                            removed_count,
+                        });
+                    }
+                    PlaylistMode::Live => {
+                        let event = HlsEvent::LiveSegmentsRemoved {
+                            delta: delta(&last, &this),
+                            last_msn: last.playlist.last_segment().unwrap().number(),
+                            this_msn: this.playlist.last_segment().unwrap().number(),
+                            removed_count
+                        };
+                        if removed_count > 1 {
+                            self.log.error(event);
+                        } else {
+                            self.log.warning(event);
+                        }
+                    }
                 }
-
             } else {
                 // we can only perform these checks when the MSN values are sane,
                 self.check_manifest_history_invariant(last, this);
@@ -244,6 +437,36 @@ impl<L: EventSink<Extra = HlsEvent>, M: Metric> MediaPlaylistCheck<L, M> {
         }
     }
 
+    /// Cross-check the playlist-level `EXT-X-DISCONTINUITY-SEQUENCE` against the segments that
+    /// have left the sliding window.  A packager that lets the counter desync from the window
+    /// breaks a player's ability to map segments onto a stable timeline.
+    fn check_discontinuity_sequence(&mut self, last: &PlaylistInfo, this: &PlaylistInfo, dropped_discontinuities: usize) {
+        // drive the check off the remembered sequence rather than the previous playlist snapshot,
+        // falling back to the previous snapshot only if no sequence has been recorded yet (the
+        // first playlist seen seeds it)
+        let last_seq = self.last_discontinuity_sequence
+            .unwrap_or(last.playlist.discontinuity_sequence);
+        let this_seq = this.playlist.discontinuity_sequence;
+        if this_seq < last_seq {
+            self.log.error(HlsEvent::DiscontinuitySequenceWentBackwards {
+                delta: delta(last, this),
+                last_discontinuity_sequence: last_seq,
+                this_discontinuity_sequence: this_seq,
+            });
+        } else {
+            let expected = last_seq + dropped_discontinuities as u64;
+            if this_seq != expected {
+                self.log.error(HlsEvent::DiscontinuitySequenceInconsistent {
+                    delta: delta(last, this),
+                    last_discontinuity_sequence: last_seq,
+                    this_discontinuity_sequence: this_seq,
+                    expected_discontinuity_sequence: expected,
+                });
+            }
+        }
+        self.last_discontinuity_sequence = Some(this_seq);
+    }
+
     fn check_stale(&mut self, this: &PlaylistInfo) {
         let this_msn = this.playlist.last_segment().map(|s| s.number() );
         if let (Some(final_msn), Some(this_msn)) = (self.final_msn, this_msn) {
@@ -281,9 +504,168 @@ impl<L: EventSink<Extra = HlsEvent>, M: Metric> MediaPlaylistCheck<L, M> {
         } else {
             0
         };
+        self.record_observed_msns(this, this.playlist.segments().skip(skip));
         self.timeline.append_new_segments(this.playlist.segments().skip(skip));
     }
 
+    /// Fold the media-sequence-numbers of freshly appended segments into the lifetime
+    /// [`SequenceSet`].  The union of everything ever served must form a single contiguous run
+    /// from the first segment seen to the current live edge; a forward jump leaves a
+    /// [`HlsEvent::MediaSequenceGap`], and a number that reappears with a different URI than it
+    /// was first served with is reported as [`HlsEvent::MediaSequenceReused`].
+    fn record_observed_msns(&mut self, this: &PlaylistInfo, segments: impl Iterator<Item = hls_m3u8::parser::MyMediaSegment>) {
+        for seg in segments {
+            let msn = seg.number() as u64;
+            let uri = seg.uri().to_string();
+            if self.observed_msns.contains(msn) {
+                // a number we've already served is only a problem if it now points at different
+                // media; the same URI reappearing is a benign duplicate observation
+                if self.observed_uris.get(&msn).map_or(false, |seen| seen != &uri) {
+                    self.log.error(HlsEvent::MediaSequenceReused {
+                        req_id: this.href.clone(),
+                        msn: seg.number(),
+                        uri,
+                    });
+                }
+                continue;
+            }
+            if let Some(edge) = self.msn_live_edge {
+                if msn > edge + 1 {
+                    self.log.error(HlsEvent::MediaSequenceGap {
+                        missing_start: edge + 1,
+                        missing_count: msn - edge - 1,
+                    });
+                }
+            }
+            self.observed_msns.insert(msn, 1);
+            self.observed_uris.insert(msn, uri);
+            self.msn_live_edge = Some(self.msn_live_edge.map_or(msn, |e| e.max(msn)));
+        }
+    }
+
+    /// Validate the `EXT-X-KEY` state against its stream-lifetime invariants.  The encryption
+    /// method must not flip between `NONE` and `AES-128`/`SAMPLE-AES` mid-stream (a frequent cause
+    /// of player decryption failures), the `KEYFORMAT`/`KEYFORMATVERSIONS` pair must stay stable
+    /// once established, and an absent explicit `IV` leaves the player to derive it implicitly from
+    /// the media sequence number, which is worth a warning.  Only newly appended segments are
+    /// examined so unchanged keys aren't re-alerted on every poll.  A single segment may legally
+    /// carry several `EXT-X-KEY` tags (multi-DRM, e.g. Widevine + PlayReady + FairPlay with
+    /// distinct `KEYFORMAT`s), so the method and key-format *sets* are compared segment-to-segment
+    /// rather than key-to-key within one segment.
+    fn check_encryption(&mut self, this: &PlaylistInfo, prev_last_msn: Option<usize>) {
+        for seg in this.playlist.segments() {
+            if let Some(prev) = prev_last_msn {
+                if seg.number() <= prev {
+                    continue;
+                }
+            }
+            let mut methods = Vec::new();
+            let mut formats = Vec::new();
+            for key in seg.keys() {
+                let method = key.method();
+                methods.push(format!("{:?}", method));
+                if method == hls_m3u8::types::EncryptionMethod::None {
+                    continue;
+                }
+                formats.push((
+                    key.key_format().map(|f| f.to_string()),
+                    key.key_format_versions().map(|v| v.to_string()),
+                ));
+                if key.iv().is_none() && !self.warned_missing_iv {
+                    self.log.warning(HlsEvent::MissingInitializationVector {
+                        req_id: this.href.clone(),
+                        msn: seg.number(),
+                    });
+                    self.warned_missing_iv = true;
+                }
+            }
+            methods.sort();
+            formats.sort();
+
+            if let Some(last_methods) = self.last_encryption_methods.as_ref() {
+                if *last_methods != methods {
+                    self.log.error(HlsEvent::EncryptionMethodChanged {
+                        req_id: this.href.clone(),
+                        msn: seg.number(),
+                        last_method: last_methods.join(", "),
+                        this_method: methods.join(", "),
+                    });
+                }
+            }
+            self.last_encryption_methods = Some(methods);
+
+            if let Some(last_formats) = self.last_key_formats.as_ref() {
+                if *last_formats != formats {
+                    self.log.error(HlsEvent::KeyFormatChanged {
+                        req_id: this.href.clone(),
+                        msn: seg.number(),
+                        last_key_format: join_key_formats(last_formats),
+                        this_key_format: join_key_formats(&formats),
+                    });
+                }
+            }
+            self.last_key_formats = Some(formats);
+        }
+    }
+
+    /// The spec requires every segment's `EXTINF`, rounded to the nearest whole second, to be no
+    /// greater than the playlist's `EXT-X-TARGETDURATION`; players size their buffers on that
+    /// bound.  Only segments appended since the previous refresh are evaluated, so a segment that
+    /// was already present is not re-alerted on each poll.
+    fn check_segment_target_duration(&mut self, this: &PlaylistInfo, prev_last_msn: Option<usize>) {
+        let target_duration = this.playlist.target_duration;
+        let target_secs = round_seconds(target_duration);
+        for seg in this.playlist.segments() {
+            if let Some(prev) = prev_last_msn {
+                if seg.number() <= prev {
+                    continue;
+                }
+            }
+            let duration = seg.duration().duration();
+            if round_seconds(duration) > target_secs {
+                self.log.error(HlsEvent::SegmentExceedsTargetDuration {
+                    req_id: this.href.clone(),
+                    msn: seg.number(),
+                    segment_duration_millis: duration.as_millis() as u64,
+                    target_duration_millis: target_duration.as_millis() as u64,
+                });
+            }
+        }
+    }
+
+    /// Verify that `EXT-X-PROGRAM-DATE-TIME` values stay internally consistent: within a run of
+    /// non-discontinuous segments, the PDT of an explicitly-tagged segment must match the PDT of
+    /// the most recent anchor segment plus the accumulated `EXTINF` durations in between.  A jump
+    /// is permitted only across an `EXT-X-DISCONTINUITY`, so the accumulation is reset there.
+    /// Segments without the tag don't act as anchors but still contribute their duration.  The
+    /// accumulator runs over the whole window so the anchor state is correct, but only segments
+    /// appended since the previous refresh are alerted on, so a drifting segment isn't re-reported
+    /// on every poll for as long as it lingers in the window.
+    fn check_program_date_time_drift(&mut self, this: &PlaylistInfo, prev_last_msn: Option<usize>) {
+        let tolerance = this.playlist.target_duration / 2;
+        let mut pdt = PdtTracker::new();
+        for seg in this.playlist.segments() {
+            // `expected` is the PDT the accumulation predicted for this segment's start before any
+            // explicit re-anchor — the value an explicit tag is drift-checked against
+            let (_, expected) = pdt.advance(seg.has_discontinuity(), seg.program_date_time(), seg.duration().duration());
+            if let Some(prev) = prev_last_msn {
+                if seg.number() <= prev {
+                    continue;
+                }
+            }
+            if let (Some(observed), Some(expected)) = (seg.program_date_time(), expected) {
+                let drift = abs_delta(observed, expected);
+                if drift > tolerance {
+                    self.log.error(HlsEvent::ProgramDateTimeDrift {
+                        req_id: this.href.clone(),
+                        msn: seg.number(),
+                        drift_millis: drift.as_millis() as u64,
+                    });
+                }
+            }
+        }
+    }
+
     fn check_manifest_history_invariant(&mut self, last: &PlaylistInfo, this: &PlaylistInfo) {
         let skip = this.playlist.media_sequence - last.playlist.media_sequence;
         let last_segments = last.playlist.segments()
@@ -329,6 +711,14 @@ impl<L: EventSink<Extra = HlsEvent>, M: Metric> MediaPlaylistCheck<L, M> {
                 this_duration_millis: this_seg.duration().duration().as_millis() as u64,
             });
         }
+        if last_seg.program_date_time() != this_seg.program_date_time() {
+            self.log.error(HlsEvent::ManifestHistoryChangedProgramDateTime {
+                delta: delta(last, this),
+                msn: this_seg.number(),
+                last_program_date_time: last_seg.program_date_time().map(|p| p.to_rfc3339()),
+                this_program_date_time: this_seg.program_date_time().map(|p| p.to_rfc3339()),
+            });
+        }
         if last_seg.byte_range() != this_seg.byte_range() {
             self.log.error(HlsEvent::ManifestHistoryChangedSegmentByterange {
                 delta: delta(last, this),
@@ -376,6 +766,182 @@ impl<L: EventSink<Extra = HlsEvent>, M: Metric> MediaPlaylistCheck<L, M> {
     }
 }
 
+/// Resolves each segment's `EXT-X-PROGRAM-DATE-TIME` by the anchor-and-accumulate rule shared by
+/// the drift check and the rendition sync snapshot: an explicitly-tagged segment re-anchors the
+/// clock, following segments derive their PDT from that anchor plus the accumulated `EXTINF`
+/// durations, and an `EXT-X-DISCONTINUITY` drops the anchor because the timeline is allowed to jump
+/// there.  Keeping this in one place stops the two callers drifting apart.
+struct PdtTracker {
+    /// (anchor PDT, duration accumulated since the anchor segment began)
+    anchor: Option<(chrono::DateTime<chrono::FixedOffset>, time::Duration)>,
+}
+
+impl PdtTracker {
+    fn new() -> PdtTracker {
+        PdtTracker { anchor: None }
+    }
+
+    /// Advance over one segment in playlist order, returning `(derived, expected)`.  `derived` is
+    /// the segment's effective PDT — its explicit tag if present, otherwise the anchor plus the
+    /// accumulated durations.  `expected` is what the accumulation predicted for the segment's
+    /// start *before* any explicit re-anchor, i.e. the value an explicit tag is drift-checked
+    /// against.
+    fn advance(
+        &mut self,
+        discontinuity: bool,
+        explicit: Option<chrono::DateTime<chrono::FixedOffset>>,
+        duration: time::Duration,
+    ) -> (Option<chrono::DateTime<chrono::FixedOffset>>, Option<chrono::DateTime<chrono::FixedOffset>>) {
+        // a discontinuity is the one place the PDT is allowed to jump, so drop the anchor
+        if discontinuity {
+            self.anchor = None;
+        }
+        let expected = self.anchor.and_then(|(a, acc)| {
+            chrono::Duration::from_std(acc).ok().map(|d| a + d)
+        });
+        // re-anchor on every segment that carries an explicit PDT
+        if let Some(pdt) = explicit {
+            self.anchor = Some((pdt, time::Duration::from_secs(0)));
+        }
+        let derived = explicit.or(expected);
+        if let Some((_, ref mut acc)) = self.anchor {
+            *acc += duration;
+        }
+        (derived, expected)
+    }
+}
+
+/// Render a segment's set of `KEYFORMAT` values as a single comma-separated string for reporting,
+/// or `None` when none of the keys carried a `KEYFORMAT`.
+fn join_key_formats(formats: &[(Option<String>, Option<String>)]) -> Option<String> {
+    let joined = formats.iter()
+        .filter_map(|(format, _)| format.clone())
+        .collect::<Vec<_>>()
+        .join(", ");
+    if joined.is_empty() {
+        None
+    } else {
+        Some(joined)
+    }
+}
+
+/// Round a duration to the nearest whole second using the HLS rule: a sub-500ms remainder rounds
+/// down, anything from 500ms up rounds up.
+fn round_seconds(d: time::Duration) -> u64 {
+    (d.as_millis() as u64 + 500) / 1000
+}
+
+/// A snapshot of one rendition's timeline alignment, produced by [`MediaPlaylistCheck::sync_state`]
+/// and fed to the [`RenditionCoordinator`] once per refresh cycle.  PDT values are resolved by the
+/// same anchor-and-accumulate rule used elsewhere, so segments without an explicit
+/// `EXT-X-PROGRAM-DATE-TIME` still contribute a derived instant.
+#[derive(Clone)]
+pub struct RenditionSyncState {
+    href: HttpRef,
+    first_program_date_time: Option<chrono::DateTime<chrono::FixedOffset>>,
+    last_program_date_time: Option<chrono::DateTime<chrono::FixedOffset>>,
+    /// PDT of each `EXT-X-DISCONTINUITY` boundary in the current window that carries an explicit
+    /// `EXT-X-PROGRAM-DATE-TIME`; a boundary without one has no resolvable PDT and is omitted.
+    discontinuity_boundaries: Vec<chrono::DateTime<chrono::FixedOffset>>,
+    target_duration: time::Duration,
+}
+
+/// Coordinates the media playlists making up the renditions of a single `EXT-X-STREAM-INF` master,
+/// checking that they stay aligned — the property an ABR player relies on to switch bitrates
+/// without a visible glitch.  Callers [`observe`](Self::observe) each rendition's latest
+/// [`RenditionSyncState`] after its refresh, then call [`tick`](Self::tick) to run the pairwise
+/// alignment pass.
+pub struct RenditionCoordinator<L: EventSink<Extra = HlsEvent>> {
+    log: L,
+    /// maximum permitted live-edge skew between renditions, as a multiple of target duration
+    max_skew_target_durations: u32,
+    renditions: Vec<(String, RenditionSyncState)>,
+}
+
+impl<L: EventSink<Extra = HlsEvent>> RenditionCoordinator<L> {
+    pub fn new(log: L, max_skew_target_durations: u32) -> RenditionCoordinator<L> {
+        RenditionCoordinator {
+            log,
+            max_skew_target_durations,
+            renditions: Vec::new(),
+        }
+    }
+
+    /// Record the latest sync state for one rendition ahead of the next [`tick`](Self::tick).
+    pub fn observe(&mut self, rendition: String, state: RenditionSyncState) {
+        if let Some(existing) = self.renditions.iter_mut().find(|(name, _)| name == &rendition) {
+            existing.1 = state;
+        } else {
+            self.renditions.push((rendition, state));
+        }
+    }
+
+    /// Run the alignment pass across every pair of renditions observed this cycle.
+    pub fn tick(&mut self) {
+        for i in 0..self.renditions.len() {
+            for j in (i + 1)..self.renditions.len() {
+                self.check_pair(i, j);
+            }
+        }
+    }
+
+    fn check_pair(&mut self, i: usize, j: usize) {
+        let name_a = self.renditions[i].0.clone();
+        let name_b = self.renditions[j].0.clone();
+        let a = self.renditions[i].1.clone();
+        let b = self.renditions[j].1.clone();
+        let pair_delta = || Delta {
+            before: ManifestRef { req_id: a.href.clone(), line: None },
+            after: ManifestRef { req_id: b.href.clone(), line: None },
+        };
+        // live-edge skew: the renditions must expose overlapping PDT ranges, so compare the most
+        // recent PDT each one publishes
+        if let (Some(edge_a), Some(edge_b)) = (a.last_program_date_time, b.last_program_date_time) {
+            let skew = abs_delta(edge_a, edge_b);
+            let tolerance = a.target_duration.max(b.target_duration) * self.max_skew_target_durations;
+            // the windows should overlap in PDT space; if one rendition's earliest segment is newer
+            // than the other's live edge they share no common wall-clock moment at all
+            let disjoint = match (a.first_program_date_time, b.first_program_date_time) {
+                (Some(start_a), Some(start_b)) => start_a > edge_b || start_b > edge_a,
+                _ => false,
+            };
+            if skew > tolerance || disjoint {
+                self.log.error(HlsEvent::RenditionsOutOfSync {
+                    delta: pair_delta(),
+                    rendition_a: name_a.clone(),
+                    rendition_b: name_b.clone(),
+                    skew_millis: skew.as_millis() as u64,
+                });
+            }
+        }
+        // every discontinuity boundary in one rendition must have a matching boundary at the same
+        // PDT (within a target duration) in the other
+        // Discontinuities are matched purely on PDT: a boundary at instant T in one rendition must
+        // have a counterpart within a target duration of T in the other.  The raw
+        // EXT-X-DISCONTINUITY-SEQUENCE counters are deliberately *not* compared — renditions of one
+        // master legitimately run different DVR/window depths and so expose different counter
+        // values even when perfectly aligned.
+        let tolerance = a.target_duration.max(b.target_duration);
+        let misaligned = a.discontinuity_boundaries.iter().any(|t| {
+            !b.discontinuity_boundaries.iter().any(|u| abs_delta(*t, *u) <= tolerance)
+        }) || b.discontinuity_boundaries.iter().any(|u| {
+            !a.discontinuity_boundaries.iter().any(|t| abs_delta(*t, *u) <= tolerance)
+        });
+        if misaligned {
+            self.log.error(HlsEvent::RenditionDiscontinuityMisaligned {
+                delta: pair_delta(),
+                rendition_a: name_a,
+                rendition_b: name_b,
+            });
+        }
+    }
+}
+
+/// Absolute wall-clock distance between two PDT instants.
+fn abs_delta(a: chrono::DateTime<chrono::FixedOffset>, b: chrono::DateTime<chrono::FixedOffset>) -> time::Duration {
+    (a - b).to_std().unwrap_or_default().max((b - a).to_std().unwrap_or_default())
+}
+
 fn header_val<T: FromStr>(header: &HeaderValue) -> Option<T> {
     header.to_str().ok()?
         .parse().ok()
@@ -383,4 +949,80 @@ fn header_val<T: FromStr>(header: &HeaderValue) -> Option<T> {
 
 fn age(headers: &hyper::HeaderMap) -> Option<u64> {
     header_val(headers.get(hyper::header::AGE)?)
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_seconds_follows_hls_rounding_rule() {
+        assert_eq!(round_seconds(time::Duration::from_millis(0)), 0);
+        assert_eq!(round_seconds(time::Duration::from_millis(499)), 0);
+        assert_eq!(round_seconds(time::Duration::from_millis(500)), 1);
+        assert_eq!(round_seconds(time::Duration::from_millis(1499)), 1);
+        assert_eq!(round_seconds(time::Duration::from_millis(1500)), 2);
+        assert_eq!(round_seconds(time::Duration::from_secs(4)), 4);
+    }
+
+    #[test]
+    fn sequence_set_coalesces_adjacent_and_overlapping_spans() {
+        let mut set = SequenceSet::new();
+        set.insert(0, 3);
+        assert!(set.contains(0) && set.contains(2));
+        assert!(!set.contains(3));
+        assert_eq!(set.spans.len(), 1);
+
+        // adjacent run [3, 5) touches the existing [0, 3) and must merge into one span
+        set.insert(3, 2);
+        assert_eq!(set.spans.len(), 1);
+        assert!(set.contains(4));
+
+        // overlapping run is absorbed without growing the span count
+        set.insert(2, 2);
+        assert_eq!(set.spans.len(), 1);
+    }
+
+    #[test]
+    fn sequence_set_keeps_holes_and_stays_ordered() {
+        let mut set = SequenceSet::new();
+        // insert out of order to exercise the ordered re-emit
+        set.insert(10, 2);
+        set.insert(0, 3);
+        assert_eq!(set.spans.len(), 2);
+        assert_eq!(set.spans[0].start, 0);
+        assert_eq!(set.spans[1].start, 10);
+        // the gap between the two runs is genuinely absent
+        assert!(!set.contains(3));
+        assert!(!set.contains(9));
+        assert!(set.contains(11));
+    }
+
+    #[test]
+    fn pdt_tracker_accumulates_and_resets_at_discontinuity() {
+        let t0 = chrono::DateTime::parse_from_rfc3339("2021-06-01T00:00:00+00:00").unwrap();
+        let at = |secs: i64| t0 + chrono::Duration::seconds(secs);
+        let six = time::Duration::from_secs(6);
+        let mut pdt = PdtTracker::new();
+
+        // first explicit segment anchors the clock; nothing preceded it to predict against
+        assert_eq!(pdt.advance(false, Some(t0), six), (Some(t0), None));
+        // following segments derive their PDT from the anchor plus accumulated EXTINF
+        assert_eq!(pdt.advance(false, None, six), (Some(at(6)), Some(at(6))));
+        assert_eq!(pdt.advance(false, None, six), (Some(at(12)), Some(at(12))));
+        // an explicit tag that matches the prediction leaves `expected` equal to it
+        assert_eq!(pdt.advance(false, Some(at(18)), six), (Some(at(18)), Some(at(18))));
+        // a discontinuity drops the anchor, so neither a derived nor expected value is produced
+        assert_eq!(pdt.advance(true, None, six), (None, None));
+        // the next explicit segment re-anchors from scratch
+        assert_eq!(pdt.advance(false, Some(at(100)), six), (Some(at(100)), None));
+    }
+
+    #[test]
+    fn only_event_to_vod_with_endlist_is_a_legal_transition() {
+        assert!(legal_mode_transition(PlaylistMode::Event, PlaylistMode::Vod, true));
+        assert!(!legal_mode_transition(PlaylistMode::Event, PlaylistMode::Vod, false));
+        assert!(!legal_mode_transition(PlaylistMode::Live, PlaylistMode::Vod, true));
+        assert!(!legal_mode_transition(PlaylistMode::Event, PlaylistMode::Live, true));
+        assert!(!legal_mode_transition(PlaylistMode::Vod, PlaylistMode::Event, true));
+    }
+}